@@ -2,16 +2,106 @@ use crate::cli::{Opts, ParseFailure};
 use std::fmt;
 use std::io::{self, Write};
 use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use atty;
-use termcolor::{self, Color, ColorSpec, StandardStream, WriteColor};
+use termcolor::{self, ColorSpec, StandardStream, WriteColor};
+
+use style::Style;
 
 /// Inspiration/partial implementations taken from the Cargo source at
 /// [cargo/core/shell.rs](https://github.com/rust-lang/cargo/blob/53094e32b11c57a917f3ec3a48f29f388583ca3b/src/cargo/core/shell.rs)
 
+/// Named, semantic output styles, centralizing the color/justification
+/// choices that used to be hard-coded at each `Shell` call site. Mirrors
+/// Cargo's `refactor(shell): Centralize Shell styling` work, so that new
+/// message categories can be added here without touching every method.
+mod style {
+    use termcolor::Color;
+
+    /// A color plus whether the status text should be right-justified to
+    /// `JUSTIFY_STATUS_LEN` columns.
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct Style {
+        pub color:     Color,
+        pub justified: bool,
+    }
+
+    /// Style of a right-aligned, in-progress action (e.g. `Collecting`).
+    pub const STATUS: Style = Style {
+        color:     Color::Green,
+        justified: true,
+    };
+    /// Style of a bare section header with no trailing message.
+    pub const HEADER: Style = Style {
+        color:     Color::Cyan,
+        justified: true,
+    };
+    /// Style of a `(error)` message.
+    pub const ERROR: Style = Style {
+        color:     Color::Red,
+        justified: true,
+    };
+    /// Style of a `(warning)` message.
+    pub const WARNING: Style = Style {
+        color:     Color::Yellow,
+        justified: true,
+    };
+    /// Style of a `(note)` message.
+    pub const NOTE: Style = Style {
+        color:     Color::Cyan,
+        justified: true,
+    };
+}
+
 /// Maximum length of status string when being justified
 const JUSTIFY_STATUS_LEN: usize = 12usize;
 
+/// Width assumed for non-tty streams (pipes, CI logs) so that wrapping still
+/// happens at a sane default instead of being disabled entirely
+const GUESSED_WIDTH: usize = 80usize;
+
+/// Environment variable that, when set to a valid number, forces `TtyWidth`
+/// to `Known(n)` regardless of the stream's actual tty-ness. Used to make
+/// width-dependent output deterministic in tests.
+const TTY_WIDTH_OVERRIDE_VAR: &str = "RADVISOR_TTY_WIDTH";
+
+/// The detected width of an output stream, following Cargo's model of
+/// distinguishing a real, measured terminal width from a guessed fallback
+/// used when no tty is present.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TtyWidth {
+    /// Not a tty, and no fallback width should be assumed (used for plain
+    /// `Write` sinks that aren't necessarily terminal-shaped).
+    NoTty,
+    /// A real width, queried from the terminal.
+    Known(usize),
+    /// Not backed by a real measurement, but a reasonable default to wrap to
+    /// anyway.
+    Guess(usize),
+}
+
+impl TtyWidth {
+    /// Checks the `RADVISOR_TTY_WIDTH` environment variable for an override,
+    /// returning `Known(n)` if it is set to a valid width.
+    fn from_env_override() -> Option<TtyWidth> {
+        std::env::var(TTY_WIDTH_OVERRIDE_VAR)
+            .ok()
+            .and_then(|val| val.parse::<usize>().ok())
+            .map(TtyWidth::Known)
+    }
+
+    /// Returns the width that wrapping/indentation should target, or `None`
+    /// if wrapping should be skipped entirely. This lets callers distinguish
+    /// "wrap to a guess" from "never wrap".
+    fn progress_max_width(self) -> Option<usize> {
+        match self {
+            TtyWidth::NoTty => None,
+            TtyWidth::Known(width) | TtyWidth::Guess(width) => Some(width),
+        }
+    }
+}
+
 /// The requested verbosity of the program output
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Verbosity {
@@ -71,11 +161,50 @@ impl ColorMode {
     }
 }
 
+/// The output format of `Shell` messages, controllable via a CLI flag. Lets
+/// radVisor run headless as a collection daemon and still be consumed
+/// reliably by log shippers/orchestrators, following Cargo's machine-message
+/// approach.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// Human-oriented, colored, possibly right-justified/wrapped text.
+    Human,
+    /// One JSON object per line, e.g. `{"kind":"warning","status":"(warning)","message":"...","ts":1234}`.
+    Json,
+}
+
+impl OutputFormat {
+    /// Determines the appropriate output format for the specified CLI
+    /// options
+    fn from_opts(opts: &Opts) -> Self {
+        match opts.json {
+            true => OutputFormat::Json,
+            false => OutputFormat::Human,
+        }
+    }
+}
+
+/// Tracks the last-drawn in-progress status line (e.g. the number of
+/// containers currently being polled, or a collection interval tick) so that
+/// it can be erased again before the next normal message is printed.
+struct Progress {
+    /// Text of the most recently drawn progress line, if any is currently
+    /// shown.
+    state: Option<String>,
+}
+
+impl Progress {
+    fn new() -> Self { Progress { state: None } }
+}
+
 /// Thread-safe handle to formatted stderr/stdout output
 pub struct Shell {
-    verbosity: Verbosity,
-    out:       Mutex<OutSink>,
-    err:       Mutex<OutSink>,
+    verbosity:   Verbosity,
+    format:      OutputFormat,
+    out:         Mutex<OutSink>,
+    err:         Mutex<OutSink>,
+    progress:    Mutex<Progress>,
+    needs_clear: Mutex<bool>,
 }
 
 #[allow(dead_code)]
@@ -84,8 +213,9 @@ impl Shell {
     /// the CLI options as necessary. Should only be called once per process.
     pub fn new(opts: &Opts) -> Self {
         Shell {
-            verbosity: Verbosity::from_opts(opts),
-            out:       Mutex::new(OutSink::Stream {
+            verbosity:   Verbosity::from_opts(opts),
+            format:      OutputFormat::from_opts(opts),
+            out:         Mutex::new(OutSink::Stream {
                 color_mode:  opts.color_mode,
                 is_tty:      atty::is(atty::Stream::Stdout),
                 stream_type: atty::Stream::Stdout,
@@ -93,7 +223,7 @@ impl Shell {
                     opts.color_mode.into_termcolor(atty::Stream::Stdout),
                 ),
             }),
-            err:       Mutex::new(OutSink::Stream {
+            err:         Mutex::new(OutSink::Stream {
                 color_mode:  opts.color_mode,
                 is_tty:      atty::is(atty::Stream::Stderr),
                 stream_type: atty::Stream::Stderr,
@@ -101,6 +231,8 @@ impl Shell {
                     opts.color_mode.into_termcolor(atty::Stream::Stderr),
                 ),
             }),
+            progress:    Mutex::new(Progress::new()),
+            needs_clear: Mutex::new(false),
         }
     }
 
@@ -108,9 +240,12 @@ impl Shell {
     /// verbosity.
     pub fn from_write(stdout: Box<dyn Write + Send>, stderr: Box<dyn Write + Send>) -> Self {
         Shell {
-            out:       Mutex::new(OutSink::Write(stdout)),
-            err:       Mutex::new(OutSink::Write(stderr)),
-            verbosity: Verbosity::Verbose,
+            out:         Mutex::new(OutSink::Write(stdout)),
+            err:         Mutex::new(OutSink::Write(stderr)),
+            verbosity:   Verbosity::Verbose,
+            format:      OutputFormat::Human,
+            progress:    Mutex::new(Progress::new()),
+            needs_clear: Mutex::new(false),
         }
     }
 
@@ -120,24 +255,97 @@ impl Shell {
         T: fmt::Display,
         U: fmt::Display,
     {
-        self.print(&status, Some(&message), Color::Green, true);
+        self.print("status", &status, Some(&message), style::STATUS);
     }
 
     pub fn status_header<T>(&mut self, status: T) -> ()
     where
         T: fmt::Display,
     {
-        self.print(&status, None, Color::Cyan, true);
+        self.print("header", &status, None, style::HEADER);
+    }
+
+    /// Draws or updates an in-progress status line on stdout, e.g. the number
+    /// of containers currently being polled or a collection interval tick.
+    /// The line is written without a trailing newline so that subsequent
+    /// calls overwrite it in place. Suppressed under `Verbosity::Quiet`, when
+    /// stdout is not a real tty (pipes/log files should never see raw `\r`
+    /// escape bytes, even though `width()` would still guess a wrap width
+    /// for them), and under `OutputFormat::Json`, since a bare status line
+    /// has no JSON framing and would corrupt a "one object per line" stream.
+    pub fn progress<T: fmt::Display>(&mut self, status: T) -> () {
+        if self.verbosity == Verbosity::Quiet || self.format == OutputFormat::Json {
+            return;
+        }
+        let mut out = self
+            .out
+            .lock()
+            .expect("Could not unwrap stdout lock: mutex poisoned");
+        if !out.is_tty() {
+            return;
+        }
+        let width = match out.width().progress_max_width() {
+            Some(width) => width,
+            None => return,
+        };
+        let text = format!("{}", status);
+        let truncated: String = text.chars().take(width).collect();
+        let previous_len = self
+            .progress
+            .lock()
+            .expect("Could not unwrap progress lock: mutex poisoned")
+            .state
+            .as_ref()
+            .map(String::len)
+            .unwrap_or(0);
+        if out.draw_progress(&truncated, previous_len).is_ok() {
+            *self
+                .progress
+                .lock()
+                .expect("Could not unwrap progress lock: mutex poisoned") = Progress {
+                state: Some(truncated),
+            };
+            *self
+                .needs_clear
+                .lock()
+                .expect("Could not unwrap needs_clear lock: mutex poisoned") = true;
+        }
     }
 
-    /// Prints a message, where the status will have `color` color, and can be
-    /// justified. The messages follows without color.
+    /// Erases any progress line currently drawn on `out`, if one is shown.
+    /// Must be called before any normal message is written to either `out`
+    /// or `err`, since both typically share the same underlying terminal.
+    /// This is always a no-op under `OutputFormat::Json`, since `progress()`
+    /// refuses to set `needs_clear` in that mode.
+    fn clear_progress(&self, out: &mut OutSink) -> () {
+        let mut needs_clear = self
+            .needs_clear
+            .lock()
+            .expect("Could not unwrap needs_clear lock: mutex poisoned");
+        if *needs_clear {
+            let width = self
+                .progress
+                .lock()
+                .expect("Could not unwrap progress lock: mutex poisoned")
+                .state
+                .take()
+                .map(|state| state.len())
+                .unwrap_or(0);
+            let _ = out.clear_progress(width);
+            *needs_clear = false;
+        }
+    }
+
+    /// Prints a message, where the status will be shown in `style`, and can
+    /// be justified. The message follows without color. `kind` is a
+    /// machine-readable tag (e.g. `"warning"`) used instead of `style` when
+    /// `self.format` is `OutputFormat::Json`.
     fn print(
         &mut self,
+        kind: &str,
         status: &dyn fmt::Display,
         message: Option<&dyn fmt::Display>,
-        color: Color,
-        justified: bool,
+        style: Style,
     ) -> () {
         match self.verbosity {
             Verbosity::Quiet => (),
@@ -146,31 +354,45 @@ impl Shell {
                     .out
                     .lock()
                     .expect("Could not unwrap stdout lock: mutex poisoned");
-                let _ = out.print(status, message, color, justified);
+                self.clear_progress(&mut out);
+                let _ = match self.format {
+                    OutputFormat::Human => out.print(status, message, style),
+                    OutputFormat::Json => out.print_json(kind, status, message),
+                };
             },
         }
     }
 
     /// Prints a red 'error' message.
     pub fn error<T: fmt::Display>(&mut self, message: T) -> () {
+        {
+            let mut out = self
+                .out
+                .lock()
+                .expect("Could not unwrap stdout lock: mutex poisoned");
+            self.clear_progress(&mut out);
+        }
         let mut err = self
             .err
             .lock()
             .expect("Could not unwrap stderr lock: mutex poisoned");
-        let _ = err.print(&"(error)", Some(&message), Color::Red, true);
+        let _ = match self.format {
+            OutputFormat::Human => err.print(&"(error)", Some(&message), style::ERROR),
+            OutputFormat::Json => err.print_json("error", &"(error)", Some(&message)),
+        };
     }
 
     /// Prints an amber 'warning' message.
     pub fn warn<T: fmt::Display>(&mut self, message: T) -> () {
         match self.verbosity {
             Verbosity::Quiet => (),
-            _ => self.print(&"(warning)", Some(&message), Color::Yellow, true),
+            _ => self.print("warning", &"(warning)", Some(&message), style::WARNING),
         };
     }
 
     /// Prints a cyan 'note' message.
     pub fn note<T: fmt::Display>(&mut self, message: T) -> () {
-        self.print(&"(note)", Some(&message), Color::Cyan, true);
+        self.print("note", &"(note)", Some(&message), style::NOTE);
     }
 
     /// Gets the current color mode.
@@ -201,6 +423,25 @@ impl Shell {
     }
 }
 
+/// Escapes a string for embedding in a JSON string literal. Minimal on
+/// purpose: radVisor has no other JSON-producing code paths, so this avoids
+/// pulling in a serialization dependency for a handful of message fields.
+fn json_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 enum OutSink {
     Write(Box<dyn Write + Send>),
     Stream {
@@ -211,62 +452,81 @@ enum OutSink {
     },
 }
 
+/// Writes a message with a status to a color-capable stream: the status
+/// comes first, bold plus the given style's color, and can be right
+/// justified to `JUSTIFY_STATUS_LEN` columns (only when `is_tty`, so a
+/// redirected stream still gets a plain `status: message` line). If `width`
+/// is known, the message is wrapped and indented under the status column;
+/// otherwise it's written as a single line. Factored out of `OutSink::print`
+/// so the wrap/offset arithmetic can be exercised directly in tests against
+/// a plain in-memory buffer, without needing a real terminal.
+fn write_styled<W: Write + WriteColor>(
+    stream: &mut W,
+    status: &dyn fmt::Display,
+    message: Option<&dyn fmt::Display>,
+    style: Style,
+    is_tty: bool,
+    width: Option<usize>,
+) -> io::Result<()> {
+    stream.reset()?;
+    stream.set_color(ColorSpec::new().set_bold(true).set_fg(Some(style.color)))?;
+
+    // Calculate the offset based on the line header
+    let offset = if style.justified && is_tty {
+        write!(stream, "{:>width$}", status, width = JUSTIFY_STATUS_LEN)?;
+        JUSTIFY_STATUS_LEN
+    } else {
+        let status_str = format!("{}", status);
+        write!(stream, "{}", status_str)?;
+        stream.set_color(ColorSpec::new().set_bold(true))?;
+        write!(stream, ":")?;
+        status_str.len() + 1
+    };
+
+    stream.reset()?;
+    match message {
+        None => write!(stream, " ")?,
+        Some(message) => {
+            // If width can be found, then wrap/indent
+            match width {
+                None => writeln!(stream, " {}", message)?,
+                Some(width) => {
+                    let formatted: String = format!("{}", message);
+                    let lines = textwrap::wrap_iter(&formatted, width - (offset + 1));
+                    let mut is_first = true;
+                    let indent = " ".repeat(offset);
+                    for line in lines {
+                        if is_first {
+                            is_first = false;
+                            writeln!(stream, " {}", line)?;
+                        } else {
+                            writeln!(stream, "{} {}", indent, line)?;
+                        }
+                    }
+                },
+            }
+        },
+    }
+    Ok(())
+}
+
 impl OutSink {
     /// Prints out a message with a status. The status comes first, and is bold
-    /// plus the given color. The status can be justified, in which case the
-    /// max width that will right align is JUSTIFY_STATUS_LEN chars.
+    /// plus the given style's color. The status can be justified, in which
+    /// case the max width that will right align is JUSTIFY_STATUS_LEN chars.
     fn print(
         &mut self,
         status: &dyn fmt::Display,
         message: Option<&dyn fmt::Display>,
-        color: Color,
-        justified: bool,
+        style: Style,
     ) -> io::Result<()> {
-        let width: Option<usize> = self.width();
+        let width: Option<usize> = self.width().progress_max_width();
         match *self {
-            OutSink::Stream { ref mut stream, is_tty,.. } => {
-                stream.reset()?;
-                stream.set_color(ColorSpec::new().set_bold(true).set_fg(Some(color)))?;
-
-                // Calculate the offset based on the line header
-                let offset = if justified && is_tty {
-                    write!(stream, "{:>width$}", status, width = JUSTIFY_STATUS_LEN)?;
-                    JUSTIFY_STATUS_LEN
-                } else {
-                    let status_str = format!("{}", status);
-                    write!(stream, "{}", status_str)?;
-                    stream.set_color(ColorSpec::new().set_bold(true))?;
-                    write!(stream, ":")?;
-                    status_str.len() + 1
-                };
-
-                stream.reset()?;
-                match message {
-                    None => write!(stream, " ")?,
-                    Some(message) => {
-                        // If width can be found, then wrap/indent
-                        match width {
-                            None => writeln!(stream, " {}", message)?,
-                            Some(width) => {
-                                let formatted: String = format!("{}", message);
-                                let lines = textwrap::wrap_iter(&formatted, width - (offset + 1));
-                                let mut is_first = true;
-                                let indent = " ".repeat(offset);
-                                for line in lines {
-                                    if is_first {
-                                        is_first = false;
-                                        writeln!(stream, " {}", line)?;
-                                    } else {
-                                        writeln!(stream, "{} {}", indent, line)?;
-                                    }
-                                }
-                            },
-                        }
-                    },
-                }
+            OutSink::Stream { ref mut stream, is_tty, .. } => {
+                write_styled(stream, status, message, style, is_tty, width)?;
             },
             OutSink::Write(ref mut w) => {
-                if justified {
+                if style.justified {
                     write!(w, "{:width$}", status, width = JUSTIFY_STATUS_LEN)?;
                 } else {
                     write!(w, "{}:", status)?;
@@ -280,15 +540,107 @@ impl OutSink {
         Ok(())
     }
 
-    /// Gets width of terminal, if applicable
-    fn width(&self) -> Option<usize> {
+    /// Prints a message as a single JSON object line, e.g.
+    /// `{"kind":"warning","status":"(warning)","message":"...","ts":1234}`,
+    /// for headless/daemon use where output is parsed by a log shipper
+    /// rather than read by a human.
+    fn print_json(
+        &mut self,
+        kind: &str,
+        status: &dyn fmt::Display,
+        message: Option<&dyn fmt::Display>,
+    ) -> io::Result<()> {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis())
+            .unwrap_or(0);
+        let message_field = match message {
+            Some(message) => format!("\"{}\"", json_escape(&format!("{}", message))),
+            None => "null".to_owned(),
+        };
+        let line = format!(
+            "{{\"kind\":\"{}\",\"status\":\"{}\",\"message\":{},\"ts\":{}}}",
+            json_escape(kind),
+            json_escape(&format!("{}", status)),
+            message_field,
+            ts
+        );
+        match self {
+            OutSink::Stream { stream, .. } => writeln!(stream, "{}", line),
+            OutSink::Write(w) => writeln!(w, "{}", line),
+        }
+    }
+
+    /// Draws or updates an in-progress status line without a trailing
+    /// newline, so that the next write to this sink overwrites it. Erases
+    /// `previous_len` columns of any prior progress text first, so that
+    /// redrawing with shorter text (e.g. `"collecting stats for 12
+    /// containers"` -> `"idle"`) doesn't leave the old line's tail visible.
+    /// Uses an erase-to-end-of-line escape on color streams, and
+    /// `previous_len` spaces on plain `Write` sinks which cannot interpret
+    /// escape sequences.
+    fn draw_progress(&mut self, text: &str, previous_len: usize) -> io::Result<()> {
+        match self {
+            OutSink::Stream { stream, .. } => {
+                write!(stream, "\r\x1b[K{}", text)?;
+                stream.flush()
+            },
+            OutSink::Write(w) => {
+                write!(w, "\r{}\r{}", " ".repeat(previous_len), text)?;
+                w.flush()
+            },
+        }
+    }
+
+    /// Erases a previously-drawn progress line of the given width so the
+    /// next message starts on a clean line. Uses an erase-to-end-of-line
+    /// escape on color streams, and `width` spaces on plain `Write` sinks
+    /// which cannot interpret escape sequences.
+    fn clear_progress(&mut self, width: usize) -> io::Result<()> {
+        match self {
+            OutSink::Stream { stream, .. } => {
+                write!(stream, "\r\x1b[K")?;
+                stream.flush()
+            },
+            OutSink::Write(w) => {
+                write!(w, "\r{}\r", " ".repeat(width))?;
+                w.flush()
+            },
+        }
+    }
+
+    /// Gets the width of the underlying stream. Real ttys yield `Known` (or
+    /// a `Guess` if their size cannot be queried), non-tty streams yield a
+    /// `Guess` so wrapping still happens, and plain `Write` sinks yield
+    /// `NoTty`. Honors the `RADVISOR_TTY_WIDTH` override
+    /// for deterministic tests.
+    fn width(&self) -> TtyWidth {
+        if let Some(forced) = TtyWidth::from_env_override() {
+            return forced;
+        }
         match self {
             OutSink::Stream {
                 is_tty: true,
                 stream_type,
                 ..
-            } => imp::width(*stream_type),
-            _ => None,
+            } => match imp::width(*stream_type) {
+                Some(width) => TtyWidth::Known(width),
+                None => TtyWidth::Guess(GUESSED_WIDTH),
+            },
+            OutSink::Stream { is_tty: false, .. } => TtyWidth::Guess(GUESSED_WIDTH),
+            OutSink::Write(_) => TtyWidth::NoTty,
+        }
+    }
+
+    /// Whether this sink is connected to a real terminal. Unlike `width()`,
+    /// this is never widened to a guess by a non-tty stream or the
+    /// `RADVISOR_TTY_WIDTH` override, so it's the right gate for features
+    /// (like `Shell::progress`) that require an actual tty rather than just
+    /// a wrap width to target.
+    fn is_tty(&self) -> bool {
+        match self {
+            OutSink::Stream { is_tty, .. } => *is_tty,
+            OutSink::Write(_) => false,
         }
     }
 }
@@ -321,8 +673,195 @@ mod imp {
     }
 }
 
-// Package is not Windows-compatible
 #[cfg(windows)]
 mod imp {
-    pub fn width(_stream: atty::Stream) -> Option<usize> { None }
+    use winapi_util::console::Console;
+
+    pub fn width(stream: atty::Stream) -> Option<usize> {
+        let mut console = match stream {
+            atty::Stream::Stdout => Console::stdout(),
+            _ => Console::stderr(),
+        }
+        .ok()?;
+        let info = console.screen_buffer_info().ok()?;
+        let rect = info.window_rect();
+        Some((rect.right - rect.left + 1) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// Serializes tests that set/unset `RADVISOR_TTY_WIDTH`, since
+    /// environment variables are process-global and `cargo test` runs tests
+    /// concurrently by default.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    /// A `Write` sink that a test can read back after the fact.
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0
+                .lock()
+                .expect("Could not unwrap test buffer lock: mutex poisoned")
+                .extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> { Ok(()) }
+    }
+
+    fn with_tty_width_override<T>(value: &str, test: impl FnOnce() -> T) -> T {
+        let _guard = ENV_LOCK
+            .lock()
+            .expect("Could not unwrap env lock: mutex poisoned");
+        std::env::set_var(TTY_WIDTH_OVERRIDE_VAR, value);
+        let result = test();
+        std::env::remove_var(TTY_WIDTH_OVERRIDE_VAR);
+        result
+    }
+
+    #[test]
+    fn progress_max_width_distinguishes_no_tty_from_guess() {
+        assert_eq!(TtyWidth::NoTty.progress_max_width(), None);
+        assert_eq!(TtyWidth::Known(100).progress_max_width(), Some(100));
+        assert_eq!(TtyWidth::Guess(GUESSED_WIDTH).progress_max_width(), Some(GUESSED_WIDTH));
+    }
+
+    #[test]
+    fn write_sink_is_no_tty_by_default() {
+        let _guard = ENV_LOCK
+            .lock()
+            .expect("Could not unwrap env lock: mutex poisoned");
+        std::env::remove_var(TTY_WIDTH_OVERRIDE_VAR);
+        let shell = Shell::from_write(Box::new(SharedBuf::default()), Box::new(SharedBuf::default()));
+        let out = shell
+            .out
+            .lock()
+            .expect("Could not unwrap stdout lock: mutex poisoned");
+        assert_eq!(out.width(), TtyWidth::NoTty);
+        assert!(!out.is_tty());
+    }
+
+    #[test]
+    fn non_tty_stream_guesses_default_width() {
+        let _guard = ENV_LOCK
+            .lock()
+            .expect("Could not unwrap env lock: mutex poisoned");
+        std::env::remove_var(TTY_WIDTH_OVERRIDE_VAR);
+        let out = OutSink::Stream {
+            color_mode:  ColorMode::Never,
+            stream:      StandardStream::stdout(termcolor::ColorChoice::Never),
+            stream_type: atty::Stream::Stdout,
+            is_tty:      false,
+        };
+        assert_eq!(out.width(), TtyWidth::Guess(GUESSED_WIDTH));
+        assert!(!out.is_tty());
+    }
+
+    #[test]
+    fn env_override_forces_known_width_regardless_of_sink() {
+        with_tty_width_override("42", || {
+            let shell =
+                Shell::from_write(Box::new(SharedBuf::default()), Box::new(SharedBuf::default()));
+            let out = shell
+                .out
+                .lock()
+                .expect("Could not unwrap stdout lock: mutex poisoned");
+            assert_eq!(out.width(), TtyWidth::Known(42));
+
+            let stream_out = OutSink::Stream {
+                color_mode:  ColorMode::Never,
+                stream:      StandardStream::stdout(termcolor::ColorChoice::Never),
+                stream_type: atty::Stream::Stdout,
+                is_tty:      false,
+            };
+            assert_eq!(stream_out.width(), TtyWidth::Known(42));
+        });
+    }
+
+    #[test]
+    fn invalid_env_override_is_ignored() {
+        with_tty_width_override("not-a-number", || {
+            let shell =
+                Shell::from_write(Box::new(SharedBuf::default()), Box::new(SharedBuf::default()));
+            let out = shell
+                .out
+                .lock()
+                .expect("Could not unwrap stdout lock: mutex poisoned");
+            assert_eq!(out.width(), TtyWidth::NoTty);
+        });
+    }
+
+    #[test]
+    fn print_wraps_message_at_the_detected_width() {
+        // Exercises the real offset/wrap arithmetic in `write_styled`
+        // (shared with `OutSink::print`) against a plain in-memory buffer,
+        // rather than re-deriving the formula in the test. The chosen
+        // message is sensitive to the exact wrap width: a wrap width of 7
+        // (the correct `width - (offset + 1)` for width=20, offset=12)
+        // splits it into three lines, while an off-by-one width of 8 would
+        // fit "dddd eee" on one line instead, so a regression in the offset
+        // math changes the grouping, not just whitespace.
+        let mut buf = termcolor::NoColor::new(Vec::new());
+        let status = "OK";
+        let message = "bb ccc dddd eee";
+        write_styled(&mut buf, &status, Some(&message), style::STATUS, true, Some(20))
+            .expect("write_styled should not fail against an in-memory buffer");
+        let output = String::from_utf8(buf.into_inner()).expect("output should be valid utf8");
+
+        let indent = " ".repeat(JUSTIFY_STATUS_LEN);
+        let expected = format!(
+            "{:>width$} bb ccc\n{indent} dddd\n{indent} eee\n",
+            status,
+            width = JUSTIFY_STATUS_LEN,
+            indent = indent,
+        );
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn progress_is_suppressed_for_non_tty_write_sink() {
+        with_tty_width_override("40", || {
+            let buf = SharedBuf::default();
+            let mut shell = Shell::from_write(Box::new(buf.clone()), Box::new(SharedBuf::default()));
+            shell.progress("42/100 containers polled");
+            assert!(buf
+                .0
+                .lock()
+                .expect("Could not unwrap test buffer lock: mutex poisoned")
+                .is_empty());
+        });
+    }
+
+    #[test]
+    fn write_sink_draw_and_clear_progress_erase_with_spaces() {
+        // `Shell::progress`/`clear_progress` never reach an `OutSink::Write`
+        // sink in practice now that `progress()` gates on `is_tty()`, so
+        // exercise the `OutSink::Write` arms of `draw_progress`/
+        // `clear_progress` directly to keep their plain-sink behavior
+        // (spaces instead of an escape code) covered.
+        let buf = SharedBuf::default();
+        let mut out = OutSink::Write(Box::new(buf.clone()));
+        out.draw_progress("hello", 0)
+            .expect("draw_progress should not fail against an in-memory buffer");
+        out.draw_progress("hi", "hello".len())
+            .expect("draw_progress should not fail against an in-memory buffer");
+        out.clear_progress("hi".len())
+            .expect("clear_progress should not fail against an in-memory buffer");
+
+        let output = String::from_utf8(
+            buf.0
+                .lock()
+                .expect("Could not unwrap test buffer lock: mutex poisoned")
+                .clone(),
+        )
+        .expect("output should be valid utf8");
+        let expected = format!("\r\rhello\r{}\rhi\r{}\r", " ".repeat(5), " ".repeat(2));
+        assert_eq!(output, expected);
+    }
 }